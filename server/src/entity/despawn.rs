@@ -0,0 +1,107 @@
+use crate::entity::{EntityDespawnEvent, EntityType};
+use crossbeam::queue::SegQueue;
+use shrev::EventChannel;
+use specs::{Entities, Entity, Read, ReadStorage, System, Write};
+
+/// Counterpart to `Spawner`: queues entities to be despawned, removing
+/// them during the handling phase of the dispatcher instead of requiring
+/// write access to `Entities`, which would serialize every system that
+/// needs to delete an entity (death, item pickup, chunk unload, etc.)
+///
+/// # Notes
+/// * This implementation is thread-safe and can be accessed simply
+/// use `Read<'a, Despawner>`. No need to have write access to it,
+/// which would block other systems.
+#[derive(Default)]
+pub struct Despawner {
+    /// The internal queue of entities to despawn.
+    queue: SegQueue<Entity>,
+}
+
+impl Despawner {
+    /// Queues an entity to be despawned.
+    pub fn despawn(&self, entity: Entity) {
+        self.queue.push(entity);
+    }
+}
+
+/// System for despawning queued entities in the `Despawner`.
+pub struct DespawnerSystem;
+
+impl<'a> System<'a> for DespawnerSystem {
+    type SystemData = (
+        Read<'a, Despawner>,
+        ReadStorage<'a, EntityType>,
+        Write<'a, EventChannel<EntityDespawnEvent>>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (despawner, types, mut despawn_events, entities) = data;
+
+        // Handle despawn requests
+        while let Ok(entity) = despawner.queue.pop() {
+            // The entity may have been reserved via `Spawner::spawn` but
+            // not processed by `SpawnerSystem` yet, in which case it has
+            // no `EntityType` component - still delete it unconditionally
+            // so the despawn request isn't silently lost, just skip the
+            // event since we don't know its type.
+            let ty = types.get(entity).copied();
+            entities.delete(entity).unwrap();
+
+            if let Some(ty) = ty {
+                // Trigger event
+                let event = EntityDespawnEvent { entity, ty };
+                despawn_events.single_write(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::{EntityDespawnEvent, EntityType};
+    use crate::testframework as t;
+
+    #[test]
+    fn test_despawn() {
+        let despawner = Despawner::default();
+        let (w, _d) = t::builder().with(DespawnerSystem, "").build();
+        let entity = w.entities().create();
+
+        despawner.despawn(entity);
+
+        let queued = despawner.queue.pop().unwrap();
+        assert_eq!(queued, entity);
+    }
+
+    #[test]
+    fn test_despawner_system() {
+        let (w, mut d) = t::builder().with(DespawnerSystem, "").build();
+
+        let mut reader = t::reader(&w);
+
+        let entity = {
+            let entities = w.entities();
+            let entity = entities.create();
+            let mut types = w.write_storage::<EntityType>();
+            types.insert(entity, EntityType::Zombie).unwrap();
+            entity
+        };
+
+        {
+            let despawner = w.fetch::<Despawner>();
+            despawner.despawn(entity);
+        }
+
+        d.dispatch(&w);
+
+        let events = t::triggered_events::<EntityDespawnEvent>(&w, &mut reader);
+        assert_eq!(events.len(), 1);
+
+        let first = events.first().unwrap();
+        assert_eq!(first.entity, entity);
+        assert_eq!(first.ty, EntityType::Zombie);
+    }
+}
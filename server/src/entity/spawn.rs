@@ -1,69 +1,320 @@
 use super::Metadata;
+use crate::entity::block::FallingBlockMarker;
 use crate::entity::item::ItemMarker;
+use crate::entity::mob::MobMarker;
+use crate::entity::projectile::{ProjectileMarker, ShooterComponent};
+use crate::entity::xp_orb::XpOrbMarker;
 use crate::entity::{EntitySpawnEvent, EntityType, PositionComponent, VelocityComponent};
 use crossbeam::queue::SegQueue;
 use feather_core::{ItemStack, Position};
 use glm::Vec3;
 use shrev::EventChannel;
-use specs::{Entities, Read, System, Write, WriteStorage};
+use specs::{Entities, Entity, LazyUpdate, Read, System, World, Write, WriteStorage};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 
 /// This type implements a convenient
 /// way to spawn entities without having to
 /// add a ton of system dependencies.
 ///
-/// It works by queueing mob spawn requests
-/// in an internal vector and lazily
+/// It works by queueing spawn requests
+/// in an internal queue and lazily
 /// creating the entities during the
 /// handling phase of the dispatcher.
 ///
+/// This is the single entry point for spawning
+/// entities of any `EntityType` - use `spawn_item`
+/// for the common item case, or `spawn` to build
+/// a request for any other entity type.
+///
 /// # Notes
 /// * This implementation is thread-safe and can
 /// be accessed simply use `Read<'a, Spawner>`.
 /// No need to have write access to it,
 /// which would block other systems.
-/// * Since entities are spawned lazily,
-/// there is no way to perform further actions
-/// on the entity until the next tick.
-#[derive(Default, Debug)]
+/// * Since entities are spawned lazily, the `Entity` returned by `spawn`
+/// and friends has none of its components until `SpawnerSystem` runs.
+/// To run code against the entity as soon as it's spawned, register an
+/// observer with `observe`. Note
+/// that observers are queued via `LazyUpdate` and only actually run on
+/// the next `World::maintain` call - callers must invoke `maintain`
+/// before the next dispatch for observers to take effect, and any
+/// other system consuming `EntitySpawnEvent` in the same dispatch pass
+/// will not see the changes an observer makes.
+#[derive(Default)]
 pub struct Spawner {
     /// The internal queue of spawn requests.
     queue: SegQueue<SpawnRequest>,
+    /// Observers to run against newly spawned entities, keyed by the
+    /// `EntityType` they were registered for.
+    observers: RwLock<HashMap<EntityType, Vec<Observer>>>,
 }
 
 impl Spawner {
-    /// Queues an item entity to be spawned.
-    pub fn spawn_item(&self, position: Position, velocity: Vec3, item: ItemStack) {
+    /// Registers an observer to be run against every entity of the given
+    /// type as soon as it's spawned by `SpawnerSystem`.
+    ///
+    /// Unlike acting on the entity on a later tick, this allows callers
+    /// to attach extra components, override metadata, or queue follow-up
+    /// spawns (e.g. a mob's equipment) without needing the `Entity`
+    /// handle up front. The observer itself only runs once `World::maintain`
+    /// is next called, so it still won't be visible to other systems
+    /// consuming `EntitySpawnEvent` within the same dispatch pass.
+    pub fn observe<F>(&self, ty: EntityType, observer: F)
+    where
+        F: Fn(Entity, &mut World) + Send + Sync + 'static,
+    {
+        self.observers
+            .write()
+            .unwrap()
+            .entry(ty)
+            .or_insert_with(Vec::new)
+            .push(Arc::new(observer));
+    }
+
+    /// Begins building a request to spawn an entity of the given type
+    /// at the given position and velocity.
+    ///
+    /// This reserves the `Entity` handle up front using atomic entity
+    /// allocation, so it's available immediately rather than only once
+    /// `SpawnerSystem` processes the request - callers can hold onto it
+    /// to wire up relationships (e.g. a thrown ender pearl remembering
+    /// its owner) in the same tick they request the spawn.
+    ///
+    /// The returned builder can be used to override the entity's
+    /// metadata or attach type-specific `Extra` data before queuing
+    /// it with `SpawnRequestBuilder::push`. If the metadata is never
+    /// overridden, `SpawnerSystem` will insert type-appropriate
+    /// default metadata instead.
+    pub fn spawn(
+        &self,
+        entities: &Entities,
+        ty: EntityType,
+        position: Position,
+        velocity: Vec3,
+    ) -> SpawnRequestBuilder {
+        SpawnRequestBuilder {
+            spawner: self,
+            request: SpawnRequest {
+                entity: entities.create(),
+                ty,
+                position,
+                velocity,
+                meta: None,
+                extra: Extra::None,
+            },
+        }
+    }
+
+    /// Queues an item entity to be spawned, returning its `Entity` handle.
+    pub fn spawn_item(
+        &self,
+        entities: &Entities,
+        position: Position,
+        velocity: Vec3,
+        item: ItemStack,
+    ) -> Entity {
         let meta = {
             let mut meta_item = super::metadata::Item::default();
             meta_item.set_item(Some(item.clone()));
             Metadata::Item(meta_item)
         };
-        let request = SpawnRequest {
-            ty: EntityType::Item,
-            position,
-            velocity,
-            meta,
 
-            extra: Extra::Item(item),
-        };
+        self.spawn(entities, EntityType::Item, position, velocity)
+            .with_metadata(meta)
+            .with_extra(Extra::Item(item))
+            .push()
+    }
+
+    /// Queues a projectile to be spawned - an arrow, snowball, ender pearl,
+    /// or any other thrown/shot entity - returning its `Entity` handle.
+    ///
+    /// `shooter` is attached to the entity as a `ShooterComponent` so the
+    /// correct "object data" field (identifying the shooter) can be sent
+    /// in the entity's spawn packet, and downstream physics/collision
+    /// systems can look up who fired it.
+    pub fn spawn_projectile(
+        &self,
+        entities: &Entities,
+        position: Position,
+        velocity: Vec3,
+        kind: EntityType,
+        shooter: Entity,
+    ) -> Entity {
+        self.spawn(entities, kind, position, velocity)
+            .with_extra(Extra::Projectile { shooter })
+            .push()
+    }
+
+    /// Begins building a descriptor for an entity of the given type at the
+    /// given position and velocity, for use with `spawn_batch`.
+    ///
+    /// Unlike `spawn`, this does not reserve an `Entity` up front;
+    /// `spawn_batch` reserves the entities for the whole batch when it
+    /// is called.
+    pub fn describe(
+        &self,
+        ty: EntityType,
+        position: Position,
+        velocity: Vec3,
+    ) -> SpawnDescriptorBuilder {
+        SpawnDescriptorBuilder {
+            descriptor: SpawnDescriptor {
+                ty,
+                position,
+                velocity,
+                meta: None,
+                extra: Extra::None,
+            },
+        }
+    }
+
+    /// Queues a whole batch of entities to be spawned at once, reserving
+    /// an `Entity` handle for each descriptor in the batch.
+    ///
+    /// This is intended for cases like XP orb bursts on mob death or item
+    /// showers from a broken container. Build up the batch with repeated
+    /// `Spawner::describe(..).build()` calls. The handles are reserved
+    /// through `Entities::create_iter`, which allocates them one at a
+    /// time, and `SpawnerSystem` inserts each entity's components into
+    /// its own storage one at a time; no storage capacity is reserved
+    /// ahead of the insert loop.
+    pub fn spawn_batch<I>(&self, entities: &Entities, descriptors: I)
+    where
+        I: IntoIterator<Item = SpawnDescriptor>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let descriptors = descriptors.into_iter();
+        let reserved = entities.create_iter().take(descriptors.len());
+
+        for (entity, descriptor) in reserved.zip(descriptors) {
+            self.queue.push(SpawnRequest {
+                entity,
+                ty: descriptor.ty,
+                position: descriptor.position,
+                velocity: descriptor.velocity,
+                meta: descriptor.meta,
+                extra: descriptor.extra,
+            });
+        }
+    }
+}
+
+/// Builder for a single spawn request, returned by `Spawner::spawn`.
+///
+/// The request is only queued once `push` is called. Dropping the builder
+/// without calling `push` silently leaks the `Entity` that was already
+/// reserved for it, so the builder is `#[must_use]`.
+#[must_use = "Spawner::spawn already reserved an Entity for this request - \
+              dropping the builder without calling `push` leaks it"]
+pub struct SpawnRequestBuilder<'a> {
+    spawner: &'a Spawner,
+    request: SpawnRequest,
+}
+
+impl<'a> SpawnRequestBuilder<'a> {
+    /// Overrides the metadata that will be attached to the spawned entity.
+    pub fn with_metadata(mut self, meta: Metadata) -> Self {
+        self.request.meta = Some(meta);
+        self
+    }
+
+    /// Sets the entity-type-specific data carried alongside the request.
+    pub fn with_extra(mut self, extra: Extra) -> Self {
+        self.request.extra = extra;
+        self
+    }
+
+    /// Queues the request to be handled by `SpawnerSystem` on its next run,
+    /// returning the `Entity` handle that was reserved for it.
+    pub fn push(self) -> Entity {
+        let entity = self.request.entity;
+        self.spawner.queue.push(self.request);
+        entity
+    }
+}
+
+/// Builder for a single entity's descriptor, returned by `Spawner::describe`.
+///
+/// Unlike `SpawnRequestBuilder`, no `Entity` is reserved until the
+/// descriptor is handed to `Spawner::spawn_batch`, so there's nothing to
+/// leak by dropping this builder - only `build` is provided, not `push`.
+pub struct SpawnDescriptorBuilder {
+    descriptor: SpawnDescriptor,
+}
+
+impl SpawnDescriptorBuilder {
+    /// Overrides the metadata that will be attached to the spawned entity.
+    pub fn with_metadata(mut self, meta: Metadata) -> Self {
+        self.descriptor.meta = Some(meta);
+        self
+    }
 
-        self.queue.push(request);
+    /// Sets the entity-type-specific data carried alongside the descriptor.
+    pub fn with_extra(mut self, extra: Extra) -> Self {
+        self.descriptor.extra = extra;
+        self
+    }
+
+    /// Finishes building the descriptor so it can be collected into a
+    /// batch for `Spawner::spawn_batch`.
+    pub fn build(self) -> SpawnDescriptor {
+        self.descriptor
     }
 }
 
+/// A callback registered with `Spawner::observe`, run against a newly
+/// spawned entity at the next `World::maintain` after `SpawnerSystem`
+/// handles it.
+type Observer = Arc<dyn Fn(Entity, &mut World) + Send + Sync>;
+
+/// An entity to be spawned as part of a batch, built via
+/// `Spawner::describe` and `SpawnDescriptorBuilder`. Unlike `SpawnRequest`,
+/// no `Entity` has been reserved for it yet - `Spawner::spawn_batch`
+/// reserves handles for the whole batch when it is called.
 #[derive(Debug, Clone)]
-struct SpawnRequest {
+pub struct SpawnDescriptor {
     ty: EntityType,
     position: Position,
     velocity: Vec3,
-    meta: Metadata,
+    /// Metadata to attach to the entity. If `None`, `SpawnerSystem`
+    /// inserts type-appropriate default metadata instead.
+    meta: Option<Metadata>,
 
     extra: Extra,
 }
 
+/// A single queued spawn request, built via `Spawner::spawn`,
+/// `SpawnRequestBuilder`, or queued by `Spawner::spawn_batch`.
 #[derive(Debug, Clone)]
-enum Extra {
+pub struct SpawnRequest {
+    /// The `Entity` handle reserved for this request at queue time via
+    /// atomic entity allocation.
+    entity: Entity,
+    ty: EntityType,
+    position: Position,
+    velocity: Vec3,
+    /// Metadata to attach to the entity. If `None`, `SpawnerSystem`
+    /// inserts type-appropriate default metadata instead.
+    meta: Option<Metadata>,
+
+    extra: Extra,
+}
+
+/// Entity-type-specific data carried by a `SpawnRequest` which doesn't
+/// belong in `Metadata`.
+#[derive(Debug, Clone)]
+pub enum Extra {
+    /// No additional data is needed for this request.
+    None,
     Item(ItemStack),
+    /// The shooter of a projectile (arrow, snowball, ender pearl, etc.),
+    /// used by `SpawnerSystem` to set up the "object data" shooter
+    /// reference in the spawn packet. The projectile's type and velocity
+    /// are already carried by the `SpawnRequest` itself.
+    Projectile {
+        shooter: Entity,
+    },
 }
 
 /// System for spawning queued requests in the `Spawner`.
@@ -77,8 +328,13 @@ impl<'a> System<'a> for SpawnerSystem {
         WriteStorage<'a, Metadata>,
         WriteStorage<'a, EntityType>,
         WriteStorage<'a, ItemMarker>,
+        WriteStorage<'a, MobMarker>,
+        WriteStorage<'a, XpOrbMarker>,
+        WriteStorage<'a, ProjectileMarker>,
+        WriteStorage<'a, ShooterComponent>,
+        WriteStorage<'a, FallingBlockMarker>,
         Write<'a, EventChannel<EntitySpawnEvent>>,
-        Entities<'a>,
+        Read<'a, LazyUpdate>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -89,13 +345,18 @@ impl<'a> System<'a> for SpawnerSystem {
             mut metadatas,
             mut types,
             mut item_markers,
+            mut mob_markers,
+            mut xp_orb_markers,
+            mut projectile_markers,
+            mut shooters,
+            mut falling_block_markers,
             mut spawn_events,
-            entities,
+            lazy,
         ) = data;
 
         // Handle spawn requests
         while let Ok(request) = spawner.queue.pop() {
-            let entity = entities.create();
+            let entity = request.entity;
 
             positions
                 .insert(
@@ -109,14 +370,60 @@ impl<'a> System<'a> for SpawnerSystem {
             velocities
                 .insert(entity, VelocityComponent(request.velocity))
                 .unwrap();
-            metadatas.insert(entity, request.meta).unwrap();
+            metadatas
+                .insert(
+                    entity,
+                    request.meta.unwrap_or_else(|| Metadata::from(request.ty)),
+                )
+                .unwrap();
             types.insert(entity, request.ty).unwrap();
 
-            match request.ty {
-                EntityType::Item => {
-                    item_markers.insert(entity, ItemMarker).unwrap();
+            if let Extra::Projectile { shooter } = request.extra {
+                projectile_markers.insert(entity, ProjectileMarker).unwrap();
+                shooters.insert(entity, ShooterComponent(shooter)).unwrap();
+            } else {
+                match request.ty {
+                    EntityType::Item => {
+                        item_markers.insert(entity, ItemMarker).unwrap();
+                    }
+                    EntityType::ExperienceOrb => {
+                        xp_orb_markers.insert(entity, XpOrbMarker).unwrap();
+                    }
+                    EntityType::Arrow => {
+                        projectile_markers.insert(entity, ProjectileMarker).unwrap();
+                    }
+                    EntityType::FallingBlock => {
+                        falling_block_markers
+                            .insert(entity, FallingBlockMarker)
+                            .unwrap();
+                    }
+                    ty if ty.is_mob() => {
+                        mob_markers.insert(entity, MobMarker).unwrap();
+                    }
+                    _ => {
+                        // Other entity types (boats, minecarts, TNT,
+                        // paintings, item frames, etc.) don't have a
+                        // dedicated marker component yet - leave them
+                        // markerless rather than guessing.
+                    }
                 }
-                _ => unimplemented!(),
+            }
+
+            // Run any observers registered for this entity type. These are
+            // dispatched through `LazyUpdate`, so they only actually execute
+            // at the next `World::maintain` call, not here - callers must
+            // call `maintain` before the next dispatch for observers to
+            // apply, and other systems reading `EntitySpawnEvent` in this
+            // same dispatch pass won't see what the observer does.
+            let observers = spawner
+                .observers
+                .read()
+                .unwrap()
+                .get(&request.ty)
+                .cloned()
+                .unwrap_or_default();
+            for observer in observers {
+                lazy.exec_mut(move |world| observer(entity, world));
             }
 
             // Trigger event
@@ -138,20 +445,41 @@ mod tests {
 
     #[test]
     fn test_spawn_item() {
+        let (w, _d) = t::builder().with(SpawnerSystem, "").build();
         let spawner = Spawner::default();
 
         let position = position!(0.0, 10.0, 1.04);
         let velocity = glm::vec3(104.0, 4.0, 10.0);
         let item = ItemStack::new(Item::EnderPearl, 4);
 
-        spawner.spawn_item(position, velocity, item);
+        let entity = spawner.spawn_item(&w.entities(), position, velocity, item);
 
         let request = spawner.queue.pop().unwrap();
+        assert_eq!(request.entity, entity);
         assert_eq!(request.ty, EntityType::Item);
         assert_eq!(request.position, position);
         assert_eq!(request.velocity, velocity);
     }
 
+    #[test]
+    fn test_spawn_generic() {
+        let (w, _d) = t::builder().with(SpawnerSystem, "").build();
+        let spawner = Spawner::default();
+
+        let position = position!(0.0, 10.0, 1.04);
+        let velocity = glm::vec3(104.0, 4.0, 10.0);
+
+        let entity = spawner
+            .spawn(&w.entities(), EntityType::Zombie, position, velocity)
+            .push();
+
+        let request = spawner.queue.pop().unwrap();
+        assert_eq!(request.entity, entity);
+        assert_eq!(request.ty, EntityType::Zombie);
+        assert_eq!(request.position, position);
+        assert_eq!(request.velocity, velocity);
+    }
+
     #[test]
     fn test_spawner_system() {
         let (w, mut d) = t::builder().with(SpawnerSystem, "").build();
@@ -162,9 +490,35 @@ mod tests {
 
         let mut reader = t::reader(&w);
 
+        let entity = {
+            let spawner = w.fetch::<Spawner>();
+            spawner.spawn_item(&w.entities(), position, velocity, item)
+        };
+
+        d.dispatch(&w);
+
+        let events = t::triggered_events::<EntitySpawnEvent>(&w, &mut reader);
+        assert_eq!(events.len(), 1);
+
+        let first = events.first().unwrap();
+        assert_eq!(first.ty, EntityType::Item);
+        assert_eq!(first.entity, entity);
+    }
+
+    #[test]
+    fn test_spawner_system_mob() {
+        let (w, mut d) = t::builder().with(SpawnerSystem, "").build();
+
+        let position = position!(0.0, 10.0, 1.04);
+        let velocity = glm::vec3(104.0, 4.0, 10.0);
+
+        let mut reader = t::reader(&w);
+
         {
             let spawner = w.fetch::<Spawner>();
-            spawner.spawn_item(position, velocity, item);
+            spawner
+                .spawn(&w.entities(), EntityType::Zombie, position, velocity)
+                .push();
         }
 
         d.dispatch(&w);
@@ -173,6 +527,94 @@ mod tests {
         assert_eq!(events.len(), 1);
 
         let first = events.first().unwrap();
-        assert_eq!(first.ty, EntityType::Item);
+        assert_eq!(first.ty, EntityType::Zombie);
+    }
+
+    #[test]
+    fn test_spawn_batch() {
+        let (w, mut d) = t::builder().with(SpawnerSystem, "").build();
+
+        let position = position!(0.0, 10.0, 1.04);
+        let velocity = glm::vec3(104.0, 4.0, 10.0);
+
+        let mut reader = t::reader(&w);
+
+        {
+            let spawner = w.fetch::<Spawner>();
+            let entities = w.entities();
+            let descriptors: Vec<_> = (0..3)
+                .map(|_| {
+                    spawner
+                        .describe(EntityType::ExperienceOrb, position, velocity)
+                        .build()
+                })
+                .collect();
+            spawner.spawn_batch(&entities, descriptors);
+        }
+
+        d.dispatch(&w);
+
+        let events = t::triggered_events::<EntitySpawnEvent>(&w, &mut reader);
+        assert_eq!(events.len(), 3);
+    }
+
+    #[test]
+    fn test_spawn_projectile() {
+        let (w, mut d) = t::builder().with(SpawnerSystem, "").build();
+
+        let position = position!(0.0, 10.0, 1.04);
+        let velocity = glm::vec3(104.0, 4.0, 10.0);
+
+        let mut reader = t::reader(&w);
+
+        let shooter = w.entities().create();
+        let projectile = {
+            let spawner = w.fetch::<Spawner>();
+            spawner.spawn_projectile(
+                &w.entities(),
+                position,
+                velocity,
+                EntityType::Arrow,
+                shooter,
+            )
+        };
+
+        d.dispatch(&w);
+
+        let events = t::triggered_events::<EntitySpawnEvent>(&w, &mut reader);
+        assert_eq!(events.len(), 1);
+
+        let first = events.first().unwrap();
+        assert_eq!(first.entity, projectile);
+        assert_eq!(first.ty, EntityType::Arrow);
+
+        let shooters = w.read_storage::<ShooterComponent>();
+        assert_eq!(shooters.get(projectile).unwrap().0, shooter);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_spawner_observer() {
+        let (mut w, mut d) = t::builder().with(SpawnerSystem, "").build();
+
+        let position = position!(0.0, 10.0, 1.04);
+        let velocity = glm::vec3(104.0, 4.0, 10.0);
+
+        let observed = Arc::new(RwLock::new(None));
+        let observed_clone = observed.clone();
+
+        {
+            let spawner = w.fetch::<Spawner>();
+            spawner.observe(EntityType::Zombie, move |entity, _world| {
+                *observed_clone.write().unwrap() = Some(entity);
+            });
+            spawner
+                .spawn(&w.entities(), EntityType::Zombie, position, velocity)
+                .push();
+        }
+
+        d.dispatch(&w);
+        w.maintain();
+
+        assert!(observed.read().unwrap().is_some());
+    }
+}